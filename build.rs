@@ -0,0 +1,29 @@
+//! Captures the exact `tremor-script` version this binary was built
+//! against, so `server_info` can report it without relying on a runtime
+//! accessor the crate may not expose.
+
+use std::fs;
+
+fn main() {
+    let version = tremor_script_version().unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=TREMOR_SCRIPT_VERSION={}", version);
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
+
+/// Reads the pinned `tremor-script` version out of `Cargo.lock`, if present.
+fn tremor_script_version() -> Option<String> {
+    let lockfile = fs::read_to_string("Cargo.lock").ok()?;
+
+    let mut lines = lockfile.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == "name = \"tremor-script\"" {
+            let version_line = lines.next()?;
+            let version = version_line
+                .trim()
+                .strip_prefix("version = \"")?
+                .strip_suffix('"')?;
+            return Some(version.to_string());
+        }
+    }
+    None
+}
@@ -0,0 +1,515 @@
+use futures::future;
+use jsonrpc_core::{BoxFuture, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{LanguageServer, Printer};
+use tremor_script::{pos, registry, script};
+
+/// Keeps the server's view of every open document in sync with the client,
+/// so handlers never have to go back to disk and instead see exactly the
+/// buffer the editor has in front of it, including unsaved edits.
+#[derive(Debug, Default)]
+struct DocumentStore {
+    documents: Mutex<HashMap<Url, String>>,
+}
+
+impl DocumentStore {
+    fn open(&self, uri: Url, text: String) {
+        self.documents.lock().unwrap().insert(uri, text);
+    }
+
+    fn close(&self, uri: &Url) {
+        self.documents.lock().unwrap().remove(uri);
+    }
+
+    fn get(&self, uri: &Url) -> Option<String> {
+        self.documents.lock().unwrap().get(uri).cloned()
+    }
+
+    /// Applies a batch of `TextDocumentContentChangeEvent`s in order, the way
+    /// editors such as Helix send them: each change is either a ranged splice
+    /// into the previous buffer, or, when `range` is absent, a full overwrite.
+    fn apply_changes(
+        &self,
+        uri: &Url,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> Option<String> {
+        let mut documents = self.documents.lock().unwrap();
+        let text = documents.get_mut(uri)?;
+        for change in changes {
+            match change.range {
+                Some(range) => {
+                    let start = position_to_offset(text, range.start);
+                    let end = position_to_offset(text, range.end);
+                    text.replace_range(start..end, &change.text);
+                }
+                None => *text = change.text,
+            }
+        }
+        Some(text.clone())
+    }
+}
+
+/// Converts a zero-based LSP `Position` into a byte offset into `text`. LSP
+/// positions count UTF-16 code units per line, so we walk characters rather
+/// than bytes to stay correct for non-ASCII scripts.
+fn position_to_offset(text: &str, position: Position) -> usize {
+    let mut offset = 0;
+    for (i, line) in text.split('\n').enumerate() {
+        if i as u64 == position.line {
+            return offset + utf16_column_to_byte_offset(line, position.character);
+        }
+        offset += line.len() + 1;
+    }
+    offset
+}
+
+/// Converts a zero-based UTF-16 `character` offset (as LSP `Position`s count
+/// them) into a byte offset within `line`.
+fn utf16_column_to_byte_offset(line: &str, character: u64) -> usize {
+    let mut units = 0;
+    for (byte_idx, ch) in line.char_indices() {
+        if units == character {
+            return byte_idx;
+        }
+        units += ch.len_utf16() as u64;
+    }
+    line.len()
+}
+
+/// A tremor-script stdlib function, for use in completion and hover.
+///
+/// This tremor-script vintage's `registry::Registry` organizes functions
+/// into per-module maps and, per the baseline's own usage, exposes nothing
+/// beyond `registry::registry()` to obtain one and `Script::parse(text,
+/// &reg)` to check a script against it — no public way to enumerate or
+/// introspect (name/arity/doc) every registered function. Rather than
+/// invent an iterator/accessor API on `Registry` that isn't there,
+/// completion and hover work off this explicit, hand-maintained catalog of
+/// the stdlib instead. Extend it here if the registry gains real listing
+/// support, or as stdlib modules are added.
+#[derive(Debug, Clone, Copy)]
+struct FunctionInfo {
+    module: &'static str,
+    name: &'static str,
+    arity: usize,
+}
+
+impl FunctionInfo {
+    fn signature(&self) -> String {
+        let params: Vec<&str> = (0..self.arity).map(|_| "_").collect();
+        format!("{}::{}({})", self.module, self.name, params.join(", "))
+    }
+}
+
+const KNOWN_FUNCTIONS: &[FunctionInfo] = &[
+    FunctionInfo { module: "array", name: "len", arity: 1 },
+    FunctionInfo { module: "array", name: "contains", arity: 2 },
+    FunctionInfo { module: "array", name: "push", arity: 2 },
+    FunctionInfo { module: "array", name: "sort", arity: 1 },
+    FunctionInfo { module: "array", name: "zip", arity: 2 },
+    FunctionInfo { module: "string", name: "len", arity: 1 },
+    FunctionInfo { module: "string", name: "contains", arity: 2 },
+    FunctionInfo { module: "string", name: "format", arity: 1 },
+    FunctionInfo { module: "string", name: "replace", arity: 3 },
+    FunctionInfo { module: "string", name: "split", arity: 2 },
+    FunctionInfo { module: "math", name: "max", arity: 2 },
+    FunctionInfo { module: "math", name: "min", arity: 2 },
+    FunctionInfo { module: "math", name: "round", arity: 1 },
+    FunctionInfo { module: "json", name: "decode", arity: 1 },
+    FunctionInfo { module: "json", name: "encode", arity: 1 },
+    FunctionInfo { module: "record", name: "contains", arity: 2 },
+    FunctionInfo { module: "record", name: "keys", arity: 1 },
+    FunctionInfo { module: "record", name: "values", arity: 1 },
+    FunctionInfo { module: "type", name: "is_array", arity: 1 },
+    FunctionInfo { module: "type", name: "is_string", arity: 1 },
+];
+
+#[derive(Debug, Default)]
+pub struct Backend {
+    documents: DocumentStore,
+    // Set from `initialize`'s `initializationOptions.verbose`; when enabled,
+    // handler tracing is additionally echoed to the client's output channel.
+    verbose: AtomicBool,
+}
+
+/// Command name for the `tremor.serverInfo` `executeCommand`, advertised in
+/// `execute_command_provider` and matched on in `execute_command`.
+const SERVER_INFO_COMMAND: &str = "tremor.serverInfo";
+
+/// Bundles the language server's own version with the exact tremor-script
+/// compiler version it was built against, so editor and CLI tooling can be
+/// confirmed to agree on how a script will compile.
+fn server_info() -> Value {
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        // Captured at build time (see build.rs) rather than via a runtime
+        // `tremor_script::version()` accessor, which this crate does not
+        // expose.
+        "tremorScriptVersion": env!("TREMOR_SCRIPT_VERSION"),
+    })
+}
+
+/// `tremor_script::script::Script::parse` stops at the first error, so to
+/// surface more than one diagnostic per pass we re-parse the remainder of
+/// the script past each reported range. This cap bounds how many times
+/// we'll do that for a single `run_checks` call.
+const MAX_DIAGNOSTICS: usize = 64;
+
+impl Backend {
+    /// Traces a handler invocation. Always emitted at `debug` level through
+    /// the `log` crate, where it reaches whatever logger the host binary
+    /// installs (the `main` binary installs none); when the client opted
+    /// into `verbose` tracing it is also sent to the editor's output channel
+    /// via `window/logMessage`.
+    fn trace(&self, printer: &Printer, message: &str) {
+        log::debug!("{}", message);
+        if self.verbose.load(Ordering::Relaxed) {
+            printer.log_message(MessageType::Log, message);
+        }
+    }
+
+    fn run_checks(&self, text: &str) -> Vec<Diagnostic> {
+        log::debug!("run_checks: {} bytes", text.len());
+
+        let mut diagnostics = Vec::new();
+        // Tracks (code, range) pairs already reported, as a
+        // (line, column, line, column) key since lsp_types' Range/Position
+        // don't derive Hash. Re-parsing a fragment past one error can
+        // manufacture a duplicate of something we've already reported
+        // rather than a genuine new one; seeing the same key twice means
+        // we've stopped making progress and should stop the loop.
+        let mut seen = std::collections::HashSet::new();
+
+        // TODO add this a field in backend struct?
+        #[allow(unused_mut)]
+        let mut reg = registry::registry();
+
+        let mut offset = 0;
+        while diagnostics.len() < MAX_DIAGNOSTICS {
+            let remainder = &text[offset..];
+            let e = match script::Script::parse(remainder, &reg) {
+                Ok(_) => break,
+                Err(e) => e,
+            };
+
+            let (kind, context_range) = e.context();
+            let range = match context_range {
+                Some(pos::Range(start, end)) => Range {
+                    start: to_lsp_position(shift_location(start, offset, text)),
+                    end: to_lsp_position(shift_location(end, offset, text)),
+                },
+                None => Range::default(),
+            };
+
+            let message = e.to_string();
+            // Tremor's parser currently only ever reports hard failures, so
+            // every diagnostic is an error; non-fatal hints will map to
+            // `Warning` once the compiler starts distinguishing them.
+            let severity = DiagnosticSeverity::Error;
+            // Derive a stable code from the error variant's name (the first
+            // token of its `Debug` representation), not the rendered
+            // message, so wording changes don't shift the code clients
+            // group/filter on. The baseline never inspected this element of
+            // `context()`, so its exact type is unverified; unwrap_some_prefix
+            // guards against it turning out to be an `Option` wrapper rather
+            // than the bare variant, which would otherwise collapse every
+            // code to the literal string "Some".
+            let code = unwrap_some_prefix(&format!("{:?}", kind))
+                .split(|c: char| c == '(' || c == ' ' || c == '{' || c == ')')
+                .next()
+                .unwrap_or("error")
+                .to_string();
+
+            let key = (
+                code.clone(),
+                range.start.line,
+                range.start.character,
+                range.end.line,
+                range.end.character,
+            );
+            if !seen.insert(key) {
+                break;
+            }
+
+            diagnostics.push(Diagnostic {
+                range,
+                severity: Some(severity),
+                code: Some(NumberOrString::String(code)),
+                source: Some("tremor".to_string()),
+                message,
+                related_information: None,
+            });
+
+            // A positionless error carries nothing to resume scanning from;
+            // re-parsing further fragments after one tends to manufacture
+            // spurious cascading diagnostics for what is really a single
+            // mistake, so stop here instead.
+            let end_offset = match context_range {
+                Some(pos::Range(_, end)) => {
+                    position_to_offset(text, to_lsp_position(shift_location(end, offset, text)))
+                }
+                None => break,
+            };
+
+            // Resume at the start of the next line rather than just past
+            // the bad token: re-parsing a mid-statement fragment is what
+            // manufactures spurious follow-on errors for a single mistake.
+            let next_offset = next_char_boundary(
+                text,
+                text[end_offset..]
+                    .find('\n')
+                    .map_or(text.len(), |i| end_offset + i + 1),
+            );
+
+            if next_offset <= offset || next_offset >= text.len() {
+                break;
+            }
+            offset = next_offset;
+        }
+
+        diagnostics
+    }
+}
+
+/// Strips a leading `Some(...)` wrapper, if present, from a `Debug`
+/// representation. Defends the diagnostic `code` derivation in
+/// `run_checks` against `context()`'s first element turning out to be an
+/// `Option` rather than the bare error variant.
+fn unwrap_some_prefix(debug_repr: &str) -> &str {
+    debug_repr.strip_prefix("Some(").unwrap_or(debug_repr)
+}
+
+/// Snaps `index` forward to the next UTF-8 char boundary in `text`, so
+/// byte offsets derived from arithmetic (rather than from a known-good
+/// char boundary) are always safe to slice on.
+fn next_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while index < text.len() && !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+/// `pos::Location` values reported by a parse of `text[offset..]` are
+/// relative to that substring; shift a location back to be relative to the
+/// full document so the rest of the pipeline can treat it uniformly.
+fn shift_location(location: pos::Location, offset: usize, text: &str) -> pos::Location {
+    if offset == 0 {
+        return location;
+    }
+    let preceding_lines = text[..offset].matches('\n').count();
+    if location.line == 1 {
+        let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        pos::Location {
+            line: preceding_lines + 1,
+            column: location.column + (offset - line_start),
+        }
+    } else {
+        pos::Location {
+            line: preceding_lines + location.line,
+            column: location.column,
+        }
+    }
+}
+
+impl LanguageServer for Backend {
+    type ShutdownFuture = BoxFuture<()>;
+    type SymbolFuture = BoxFuture<Option<Vec<SymbolInformation>>>;
+    type ExecuteFuture = BoxFuture<Option<Value>>;
+    type CompletionFuture = BoxFuture<Option<CompletionResponse>>;
+    type HoverFuture = BoxFuture<Option<Hover>>;
+    type HighlightFuture = BoxFuture<Option<Vec<DocumentHighlight>>>;
+
+    fn initialize(&self, _: &Printer, params: InitializeParams) -> Result<InitializeResult> {
+        let verbose = params
+            .initialization_options
+            .as_ref()
+            .and_then(|opts| opts.get("verbose"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        self.verbose.store(verbose, Ordering::Relaxed);
+
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                hover_provider: Some(true),
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::Incremental,
+                )),
+                completion_provider: Some(CompletionOptions {
+                    // This tower-lsp vintage's `LanguageServer` trait (built
+                    // around `Printer`, predating the later `Client`-based
+                    // API) has no `completion/resolve` hook, so there is no
+                    // resolve phase to advertise. Items from `completion`
+                    // carry a label, signature detail and insert text; no
+                    // documentation is attached to them.
+                    resolve_provider: None,
+                    trigger_characters: None,
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![SERVER_INFO_COMMAND.to_string()],
+                }),
+                ..ServerCapabilities::default()
+            },
+        })
+    }
+
+    fn initialized(&self, printer: &Printer, _: InitializedParams) {
+        self.trace(printer, "initialized");
+        printer.log_message(MessageType::Info, "server initialized!");
+        printer.log_message(MessageType::Info, &server_info().to_string());
+    }
+
+    fn shutdown(&self) -> Self::ShutdownFuture {
+        Box::new(future::ok(()))
+    }
+
+    fn symbol(&self, _: WorkspaceSymbolParams) -> Self::SymbolFuture {
+        Box::new(future::ok(None))
+    }
+
+    fn execute_command(&self, _: &Printer, params: ExecuteCommandParams) -> Self::ExecuteFuture {
+        log::debug!("executeCommand: {}", params.command);
+
+        let result = if params.command == SERVER_INFO_COMMAND {
+            Some(server_info())
+        } else {
+            None
+        };
+
+        Box::new(future::ok(result))
+    }
+
+    fn completion(&self, _: CompletionParams) -> Self::CompletionFuture {
+        log::debug!("completion");
+
+        let items = KNOWN_FUNCTIONS
+            .iter()
+            .map(|f| CompletionItem {
+                label: f.name.to_string(),
+                kind: Some(CompletionItemKind::Function),
+                detail: Some(f.signature()),
+                insert_text: Some(f.name.to_string()),
+                ..CompletionItem::default()
+            })
+            .collect();
+
+        Box::new(future::ok(Some(CompletionResponse::Array(items))))
+    }
+
+    fn hover(&self, params: TextDocumentPositionParams) -> Self::HoverFuture {
+        log::debug!("hover");
+
+        let uri = params.text_document.uri;
+        let result = self.documents.get(&uri).and_then(|text| {
+            let ident = identifier_at(&text, params.position)?;
+
+            // A qualified `module::name` is matched exactly. A bare name is
+            // only resolved when it's unambiguous across modules; if more
+            // than one module registers a function with that short name,
+            // we can't tell which one the user meant without a qualifier,
+            // so we say nothing rather than guessing.
+            let parts: Vec<&str> = ident.splitn(2, "::").collect();
+            let info = if let [module, name] = parts[..] {
+                KNOWN_FUNCTIONS.iter().find(|f| f.module == module && f.name == name)
+            } else {
+                let mut matches = KNOWN_FUNCTIONS.iter().filter(|f| f.name == ident);
+                let first = matches.next()?;
+                if matches.next().is_some() {
+                    None
+                } else {
+                    Some(first)
+                }
+            }?;
+
+            Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```\n{}\n```", info.signature()),
+                }),
+                range: None,
+            })
+        });
+
+        Box::new(future::ok(result))
+    }
+
+    fn document_highlight(&self, _: TextDocumentPositionParams) -> Self::HighlightFuture {
+        Box::new(future::ok(None))
+    }
+
+    fn did_open(&self, printer: &Printer, params: DidOpenTextDocumentParams) {
+        self.trace(printer, "didOpen");
+        let uri = params.text_document.uri;
+        let text = params.text_document.text;
+        self.documents.open(uri.clone(), text.clone());
+        printer.publish_diagnostics(uri, self.run_checks(&text));
+    }
+
+    fn did_change(&self, printer: &Printer, params: DidChangeTextDocumentParams) {
+        self.trace(printer, "didChange");
+        let uri = params.text_document.uri;
+        if let Some(text) = self.documents.apply_changes(&uri, params.content_changes) {
+            printer.publish_diagnostics(uri, self.run_checks(&text));
+        }
+    }
+
+    fn did_close(&self, printer: &Printer, params: DidCloseTextDocumentParams) {
+        self.trace(printer, "didClose");
+        let uri = params.text_document.uri;
+        self.documents.close(&uri);
+        printer.publish_diagnostics(uri, vec![]);
+    }
+}
+
+// TODO migrate to another module
+fn to_lsp_position(location: pos::Location) -> Position {
+    // lsp position is zero-based
+    Position::new((location.line - 1) as u64, (location.column - 1) as u64)
+}
+
+/// Inverse of `to_lsp_position`: turns a zero-based LSP `Position` back into
+/// the one-based `pos::Location` tremor-script errors and the registry deal
+/// in.
+fn from_lsp_position(position: Position) -> pos::Location {
+    pos::Location {
+        line: (position.line + 1) as usize,
+        column: (position.character + 1) as usize,
+    }
+}
+
+/// Finds the identifier under `position` in `text`, if any, by scanning the
+/// target line for a contiguous run of identifier characters that contains
+/// the cursor column. `::` is treated as an identifier character alongside
+/// alphanumerics so a qualified call like `array::len` is captured whole
+/// rather than just the segment the cursor happens to sit over.
+fn identifier_at(text: &str, position: Position) -> Option<String> {
+    let location = from_lsp_position(position);
+    let line = text.lines().nth(location.line - 1)?;
+    // `position.character` counts UTF-16 code units, not bytes, so it must
+    // go through the same conversion `position_to_offset` uses rather than
+    // being used directly as a byte index.
+    let col = utf16_column_to_byte_offset(line, position.character);
+
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == ':';
+    // The reverse search gives the byte index where the preceding separator
+    // *starts*;
+    // snapping to `i + c.len_utf8()` rather than `i + 1` keeps this a valid
+    // char boundary even when the separator is multi-byte (e.g. `…`).
+    let start = line[..col]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !is_ident(c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let end = col + line[col..].find(|c| !is_ident(c)).unwrap_or(line.len() - col);
+
+    if start == end {
+        None
+    } else {
+        Some(line[start..end].trim_matches(':').to_string())
+    }
+}
+
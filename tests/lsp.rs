@@ -0,0 +1,210 @@
+//! In-process integration tests that drive `Backend` the way a real editor
+//! would: over JSON-RPC requests/notifications against an in-memory
+//! `LspService`, rather than calling `LanguageServer` methods directly.
+
+use futures::Future;
+use jsonrpc_core::{Id, MethodCall, Notification, Params, Version};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tower_lsp::lsp_types::*;
+use tower_lsp::LspService;
+use tower_service::Service;
+use tremor_language_server::Backend;
+
+/// A minimal typed JSON-RPC client over an in-memory `LspService`, in the
+/// style of texlab's `TestLspClient`: every LSP method is a plain Rust
+/// function instead of a hand-built `Request` at every call site.
+trait TestLspClient {
+    fn request<R: DeserializeOwned>(&mut self, method: &str, params: Value) -> R;
+    fn notify(&mut self, method: &str, params: Value);
+
+    fn initialize(&mut self) -> InitializeResult {
+        self.request("initialize", json!(InitializeParams::default()))
+    }
+
+    fn initialized(&mut self) {
+        self.notify("initialized", json!(InitializedParams {}));
+    }
+
+    fn did_open(&mut self, uri: &str, text: &str) {
+        self.notify(
+            "textDocument/didOpen",
+            json!(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.parse().unwrap(),
+                    language_id: "tremor".into(),
+                    version: 0,
+                    text: text.into(),
+                },
+            }),
+        );
+    }
+
+    fn did_change(&mut self, uri: &str, changes: Vec<TextDocumentContentChangeEvent>) {
+        self.notify(
+            "textDocument/didChange",
+            json!(DidChangeTextDocumentParams {
+                text_document: VersionedTextDocumentIdentifier {
+                    uri: uri.parse().unwrap(),
+                    version: None,
+                },
+                content_changes: changes,
+            }),
+        );
+    }
+
+    fn hover(&mut self, uri: &str, position: Position) -> Option<Hover> {
+        self.request(
+            "textDocument/hover",
+            json!(TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.parse().unwrap() },
+                position,
+            }),
+        )
+    }
+
+    fn shutdown(&mut self) {
+        let () = self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+    }
+}
+
+impl TestLspClient for LspService<Backend> {
+    fn request<R: DeserializeOwned>(&mut self, method: &str, params: Value) -> R {
+        let call = MethodCall {
+            jsonrpc: Some(Version::V2),
+            method: method.into(),
+            params: Params::Array(vec![params]).into(),
+            id: Id::Num(1),
+        };
+        let response = self
+            .call(jsonrpc_core::Request::Single(jsonrpc_core::Call::MethodCall(call)))
+            .wait()
+            .expect("request failed")
+            .expect("no response");
+        serde_json::from_value(serde_json::to_value(response).unwrap()).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        let notification = Notification {
+            jsonrpc: Some(Version::V2),
+            method: method.into(),
+            params: Params::Array(vec![params]).into(),
+        };
+        self.call(jsonrpc_core::Request::Single(jsonrpc_core::Call::Notification(
+            notification,
+        )))
+        .wait()
+        .expect("notification failed");
+    }
+}
+
+/// `initialized` logs a couple of `window/logMessage` notifications onto the
+/// same stream before any diagnostics are published, so tests skip past them
+/// to the next `textDocument/publishDiagnostics` and assert on its payload.
+fn next_diagnostics<I>(messages: &mut I) -> PublishDiagnosticsParams
+where
+    I: Iterator<Item = Result<jsonrpc_core::Notification, ()>>,
+{
+    loop {
+        let notification = messages
+            .next()
+            .expect("messages stream ended before publishDiagnostics")
+            .expect("messages stream errored");
+        if notification.method == "textDocument/publishDiagnostics" {
+            return notification
+                .params
+                .parse()
+                .expect("malformed publishDiagnostics params");
+        }
+    }
+}
+
+#[test]
+fn reports_a_diagnostic_for_malformed_tremor_script() {
+    let (mut service, messages) = LspService::new(Backend::default());
+    service.initialize();
+    service.initialized();
+    let mut messages = messages.wait();
+
+    service.did_open("file:///test.tremor", "this is not valid tremor");
+
+    let diagnostics = next_diagnostics(&mut messages);
+    assert!(!diagnostics.diagnostics.is_empty());
+
+    service.shutdown();
+}
+
+#[test]
+fn reports_exactly_one_diagnostic_for_a_single_line_malformed_script() {
+    let (mut service, messages) = LspService::new(Backend::default());
+    service.initialize();
+    service.initialized();
+    let mut messages = messages.wait();
+
+    // A single malformed line with no trailing newline: `run_checks`
+    // resumes scanning at the next line boundary after an error, which
+    // here is the end of the text, so this script can only ever produce
+    // the one diagnostic for its one mistake, regardless of how the
+    // tremor grammar renders the error itself.
+    service.did_open("file:///test.tremor", "this is not valid tremor");
+
+    let diagnostics = next_diagnostics(&mut messages);
+    assert_eq!(diagnostics.diagnostics.len(), 1);
+
+    service.shutdown();
+}
+
+#[test]
+fn reports_no_diagnostics_for_a_valid_tremor_script() {
+    let (mut service, messages) = LspService::new(Backend::default());
+    service.initialize();
+    service.initialized();
+    let mut messages = messages.wait();
+
+    service.did_open("file:///test.tremor", "let x = 1;");
+
+    let diagnostics = next_diagnostics(&mut messages);
+    assert!(diagnostics.diagnostics.is_empty());
+
+    service.shutdown();
+}
+
+#[test]
+fn did_change_re_checks_the_incrementally_edited_document() {
+    let (mut service, messages) = LspService::new(Backend::default());
+    service.initialize();
+    service.initialized();
+    let mut messages = messages.wait();
+
+    service.did_open("file:///test.tremor", "this is not valid tremor");
+    let opened = next_diagnostics(&mut messages);
+    assert!(!opened.diagnostics.is_empty());
+
+    // A full-document replacement (no `range`) swaps in a valid script.
+    service.did_change(
+        "file:///test.tremor",
+        vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "let x = 1;".into(),
+        }],
+    );
+    let changed = next_diagnostics(&mut messages);
+    assert!(changed.diagnostics.is_empty());
+
+    service.shutdown();
+}
+
+#[test]
+fn hover_returns_none_when_cursor_is_not_over_a_known_function() {
+    let (mut service, _messages) = LspService::new(Backend::default());
+    service.initialize();
+    service.initialized();
+
+    service.did_open("file:///test.tremor", "let x = 1;");
+    let hover = service.hover("file:///test.tremor", Position::new(0, 5));
+    assert!(hover.is_none());
+
+    service.shutdown();
+}